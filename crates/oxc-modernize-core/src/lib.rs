@@ -0,0 +1,7 @@
+pub mod modernizer;
+pub mod using_declaration_transformer;
+pub mod variable_transformer;
+
+pub use modernizer::{ModernizationPass, ModernizationReport, Modernizer};
+pub use using_declaration_transformer::{TargetVersion, UsingDeclarationTransformer};
+pub use variable_transformer::SmartVarToLetVisitor;
@@ -0,0 +1,122 @@
+use oxc_ast::ast::Program;
+use oxc_ast_visit::VisitMut;
+
+/// 一个独立的现代化转换步骤，例如`var`到`let`/`const`的转换。遵循visitor +
+/// reconstructor的拆分：每个pass只是一个`VisitMut`，外加`Modernizer`需要的
+/// 元数据（名称、是否启用、执行顺序）。
+pub trait ModernizationPass<'a>: VisitMut<'a> {
+    /// 用于诊断和用户配置的pass名称。
+    fn name(&self) -> &'static str;
+
+    /// 这个pass是否参与`Modernizer`的这一次运行。
+    fn is_enabled(&self) -> bool;
+
+    /// 多个pass之间的执行顺序，数值越小越先执行。
+    fn priority(&self) -> i32;
+
+    /// 这次运行实际改动了多少处代码，汇总进`ModernizationReport`。
+    fn change_count(&self) -> usize;
+}
+
+/// 一次`Modernizer::run`的结果：每个参与运行的pass的名称和它造成的改动数量。
+#[derive(Debug, Default)]
+pub struct ModernizationReport {
+    pass_changes: Vec<(&'static str, usize)>,
+}
+
+impl ModernizationReport {
+    /// 按pass名称返回这次运行造成的改动数量。
+    pub fn pass_changes(&self) -> &[(&'static str, usize)] {
+        &self.pass_changes
+    }
+
+    /// 所有pass加起来造成的改动总数。
+    pub fn total_changes(&self) -> usize {
+        self.pass_changes.iter().map(|(_, count)| count).sum()
+    }
+}
+
+/// 多pass现代化流水线：持有一组注册好的`ModernizationPass`，共享同一个
+/// `Program`依次运行它们，而不是像之前那样只能硬编码调用单一的visitor。
+#[derive(Default)]
+pub struct Modernizer<'a> {
+    passes: Vec<Box<dyn ModernizationPass<'a> + 'a>>,
+}
+
+impl<'a> Modernizer<'a> {
+    pub fn new() -> Self {
+        Self { passes: Vec::new() }
+    }
+
+    /// 注册一个pass。`run`会按`priority()`重新排序；`priority()`相同的pass
+    /// 按注册顺序执行。
+    pub fn register(&mut self, pass: Box<dyn ModernizationPass<'a> + 'a>) -> &mut Self {
+        self.passes.push(pass);
+        self
+    }
+
+    /// 依次运行所有已启用的pass，按`priority()`从小到大排序，共享同一个
+    /// `Program`。返回每个pass造成的改动数量。
+    pub fn run(&mut self, program: &mut Program<'a>) -> ModernizationReport {
+        self.passes.sort_by_key(|pass| pass.priority());
+
+        let mut report = ModernizationReport::default();
+        for pass in self.passes.iter_mut().filter(|pass| pass.is_enabled()) {
+            pass.visit_program(program);
+            report.pass_changes.push((pass.name(), pass.change_count()));
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Modernizer;
+    use crate::variable_transformer::SmartVarToLetVisitor;
+    use oxc_allocator::Allocator;
+    use oxc_codegen::Codegen;
+    use oxc_parser::Parser;
+    use oxc_semantic::SemanticBuilder;
+    use oxc_span::SourceType;
+
+    #[test]
+    fn test_modernizer_runs_registered_pass() {
+        let allocator = Allocator::default();
+        let source_type = SourceType::from_path("test.js").unwrap();
+        let source_text = "var a = 1; console.log(a);";
+        let ret = Parser::new(&allocator, source_text, source_type).parse();
+        let mut program = ret.program;
+
+        let semantic_ret = SemanticBuilder::new().build(&program);
+        let semantic = semantic_ret.semantic;
+
+        let mut modernizer = Modernizer::new();
+        modernizer.register(Box::new(SmartVarToLetVisitor::new(&semantic)));
+        let report = modernizer.run(&mut program);
+
+        let code = Codegen::new().build(&program).code;
+        assert!(code.contains("const a = 1"), "Expected the registered pass to run and convert var to const");
+        assert_eq!(report.total_changes(), 1, "Expected exactly one declaration to be converted");
+        assert_eq!(report.pass_changes(), &[("var-to-let", 1)]);
+    }
+
+    #[test]
+    fn test_modernizer_skips_disabled_pass() {
+        let allocator = Allocator::default();
+        let source_type = SourceType::from_path("test.js").unwrap();
+        let source_text = "var a = 1; console.log(a);";
+        let ret = Parser::new(&allocator, source_text, source_type).parse();
+        let mut program = ret.program;
+
+        let semantic_ret = SemanticBuilder::new().build(&program);
+        let semantic = semantic_ret.semantic;
+
+        let mut modernizer = Modernizer::new();
+        modernizer.register(Box::new(SmartVarToLetVisitor::new(&semantic).with_enabled(false)));
+        let report = modernizer.run(&mut program);
+
+        let code = Codegen::new().build(&program).code;
+        assert!(code.contains("var a = 1"), "Expected a disabled pass not to touch the program");
+        assert!(report.pass_changes().is_empty(), "Expected a disabled pass to be skipped entirely");
+    }
+}
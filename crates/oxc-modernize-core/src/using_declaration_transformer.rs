@@ -0,0 +1,391 @@
+use oxc_allocator::Vec as ArenaVec;
+use oxc_ast::ast::{
+    BindingPatternKind, CallExpression, Expression, Statement, TryStatement, VariableDeclaration,
+    VariableDeclarationKind,
+};
+use oxc_ast_visit::{self, Visit, VisitMut};
+use oxc_semantic::Semantic;
+
+use crate::modernizer::ModernizationPass;
+
+/// 手动调用的同步清理方法名，例如`resource.close()`或`resource.dispose()`。
+const SYNC_DISPOSE_METHODS: &[&str] = &["close", "dispose"];
+/// 手动调用的异步清理方法名，例如`await resource.disposeAsync()`。
+const ASYNC_DISPOSE_METHODS: &[&str] = &["disposeAsync", "close", "dispose"];
+
+/// 目标输出的JS版本。`using`/`await using`声明是显式资源管理提案引入的语法，
+/// 只有目标版本支持它时才应该做这个转换。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TargetVersion {
+    Es2022,
+    EsNext,
+}
+
+impl TargetVersion {
+    fn supports_using_declarations(self) -> bool {
+        matches!(self, TargetVersion::EsNext)
+    }
+}
+
+/// 把`const x = acquire(); try { ... } finally { x.close(); }`这种手动资源
+/// 清理模式，重写为显式资源管理的`using x = acquire();`声明（清理是`await`的
+/// 就重写为`await using`）。
+///
+/// 参考LibJS对`using`/`await using`声明在块和`for`/`for-of`循环里的支持。
+/// 只处理最常见的「const/let声明紧跟一个没有catch、只有finally的try语句，
+/// 并且这一对语句是所在语句块里最后一对语句」的形状——`using`声明的释放时机
+/// 是离开外层块，而`try/finally`的释放时机是try语句本身结束，如果后面还有
+/// 其他语句，转换会推迟释放、改变可观察行为，所以不是最后一对就不转换。
+/// 并且要求：
+/// - `finally`块里只有一条语句，就是对同一个绑定调用清理方法；
+/// - `try`块内部没有任何地方已经手动调用过同一套清理方法（例如某个分支提前
+///   `return`之前手动`close()`）——这个pass只认「唯一释放点就是`finally`」
+///   这一种形状，不去证明`finally`是否是唯一被执行到的释放点，因为try块里
+///   已经存在手动调用就说明原作者依赖了某条路径提前释放，转换后`finally`
+///   仍然会再释放一次，造成重复释放；
+/// - 绑定本身从未被重新赋值（借助`Semantic`的写引用数据判断，与
+///   [`crate::variable_transformer::SmartVarToLetVisitor`]里的做法一致）；
+/// - 目标版本支持`using`声明，否则这个pass直接跳过。
+pub struct UsingDeclarationTransformer<'a> {
+    semantic: &'a Semantic<'a>,
+    target_version: TargetVersion,
+    enabled: bool,
+    change_count: usize,
+}
+
+impl<'a> UsingDeclarationTransformer<'a> {
+    pub fn new(semantic: &'a Semantic<'a>, target_version: TargetVersion) -> Self {
+        Self {
+            semantic,
+            target_version,
+            enabled: true,
+            change_count: 0,
+        }
+    }
+
+    /// 构建阶段设置这个pass是否启用，默认启用。
+    pub fn with_enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    /// 如果`decl`和`try_stmt`一起构成手动资源清理模式，返回新声明应该使用的
+    /// `kind`（`using`或`await using`）。不改动任何AST节点：true/false地判断
+    /// 是否匹配，实际的重写交给调用方完成。
+    fn match_resource_cleanup(
+        &self,
+        decl: &VariableDeclaration<'a>,
+        try_stmt: &TryStatement<'a>,
+    ) -> Option<VariableDeclarationKind> {
+        if !self.target_version.supports_using_declarations() {
+            return None;
+        }
+        if !matches!(decl.kind, VariableDeclarationKind::Const | VariableDeclarationKind::Let)
+            || decl.declarations.len() != 1
+        {
+            return None;
+        }
+        if try_stmt.handler.is_some() || try_stmt.finalizer.is_none() {
+            return None;
+        }
+
+        let declarator = &decl.declarations[0];
+        let BindingPatternKind::BindingIdentifier(binding) = &declarator.id.kind else {
+            return None;
+        };
+        if declarator.init.is_none() {
+            return None;
+        }
+        let symbol_id = binding.symbol_id.get()?;
+
+        // 绑定被重新赋值过：不符合"只声明一次、只释放一次"的资源管理模式。
+        let reassigned = self
+            .semantic
+            .scoping()
+            .get_resolved_references(symbol_id)
+            .any(|reference| reference.is_write());
+        if reassigned {
+            return None;
+        }
+
+        let finalizer = try_stmt.finalizer.as_ref().unwrap();
+        if finalizer.body.len() != 1 {
+            return None;
+        }
+
+        // try块内部如果已经有地方手动调用过同一套清理方法（例如某条路径提前
+        // return之前手动close()），说明`finally`不是唯一的释放点：转换成
+        // using会让这次手动调用和using自动插入的释放重复触发。`ASYNC_DISPOSE_
+        // METHODS`已经是`SYNC_DISPOSE_METHODS`的超集，检查它就够了。
+        if try_body_already_disposes(&try_stmt.block.body, binding.name.as_str(), ASYNC_DISPOSE_METHODS) {
+            return None;
+        }
+
+        let is_disposed = matches!(
+            &finalizer.body[0],
+            Statement::ExpressionStatement(expr_stmt)
+                if is_dispose_call(&expr_stmt.expression, binding.name.as_str(), SYNC_DISPOSE_METHODS)
+        );
+        let is_awaited_disposed = matches!(
+            &finalizer.body[0],
+            Statement::ExpressionStatement(expr_stmt)
+                if matches!(
+                    &expr_stmt.expression,
+                    Expression::AwaitExpression(await_expr)
+                        if is_dispose_call(&await_expr.argument, binding.name.as_str(), ASYNC_DISPOSE_METHODS)
+                )
+        );
+
+        if !is_disposed && !is_awaited_disposed {
+            return None;
+        }
+
+        if is_awaited_disposed {
+            Some(VariableDeclarationKind::AwaitUsing)
+        } else {
+            Some(VariableDeclarationKind::Using)
+        }
+    }
+}
+
+/// 判断`expression`是否是对名为`binding_name`的变量调用`methods`里某一个
+/// 无参数方法，例如`resource.close()`。
+fn is_dispose_call(expression: &Expression<'_>, binding_name: &str, methods: &[&str]) -> bool {
+    let Expression::CallExpression(call) = expression else {
+        return false;
+    };
+    call_is_dispose_call(call, binding_name, methods)
+}
+
+/// 和[`is_dispose_call`]一样的判断，但直接接收一个`CallExpression`，供
+/// 已经拿到`CallExpression`（而不是外层`Expression`）的调用方复用，
+/// 例如遍历AST时访问到的每一个调用表达式。
+fn call_is_dispose_call(call: &CallExpression<'_>, binding_name: &str, methods: &[&str]) -> bool {
+    if !call.arguments.is_empty() {
+        return false;
+    }
+    let Some(member) = call.callee.as_member_expression() else {
+        return false;
+    };
+    let Expression::Identifier(object) = member.object() else {
+        return false;
+    };
+    if object.name.as_str() != binding_name {
+        return false;
+    }
+    let Some(property_name) = member.static_property_name() else {
+        return false;
+    };
+    methods.contains(&property_name)
+}
+
+/// 遍历`statements`（一个`try`块的所有语句，递归到任意深度的嵌套block/if/
+/// for/switch/try等结构），判断内部是否存在对`binding_name`调用`methods`
+/// 里任意一个方法——用于检测「`finally`之外还有地方手动释放过同一个资源」
+/// 的重复释放风险。
+fn try_body_already_disposes<'a>(
+    statements: &ArenaVec<'a, Statement<'a>>,
+    binding_name: &str,
+    methods: &[&str],
+) -> bool {
+    struct DisposeCallFinder<'b> {
+        binding_name: &'b str,
+        methods: &'b [&'b str],
+        found: bool,
+    }
+
+    impl<'a, 'b> Visit<'a> for DisposeCallFinder<'b> {
+        fn visit_call_expression(&mut self, call: &CallExpression<'a>) {
+            if call_is_dispose_call(call, self.binding_name, self.methods) {
+                self.found = true;
+            }
+            oxc_ast_visit::walk::walk_call_expression(self, call);
+        }
+    }
+
+    let mut finder = DisposeCallFinder { binding_name, methods, found: false };
+    for statement in statements {
+        finder.visit_statement(statement);
+    }
+    finder.found
+}
+
+impl<'a> VisitMut<'a> for UsingDeclarationTransformer<'a> {
+    fn visit_statements(&mut self, statements: &mut ArenaVec<'a, Statement<'a>>) {
+        let mut index = 0;
+        while index + 1 < statements.len() {
+            // `using`在离开外层块时才释放，而原来的`try/finally`在try语句
+            // 结束的地方就释放了。只有当这一对语句是所在块里最后一对语句时，
+            // 两种释放时机才重合；后面还有别的语句的话，转换会推迟释放、
+            // 让那些语句在资源仍然持有的情况下运行，改变了可观察行为。
+            let is_last_pair_in_block = index + 2 == statements.len();
+            let new_kind = match (&statements[index], &statements[index + 1]) {
+                (Statement::VariableDeclaration(decl), Statement::TryStatement(try_stmt))
+                    if is_last_pair_in_block =>
+                {
+                    self.match_resource_cleanup(decl, try_stmt)
+                }
+                _ => None,
+            };
+
+            let Some(new_kind) = new_kind else {
+                index += 1;
+                continue;
+            };
+
+            if let Statement::VariableDeclaration(decl) = &mut statements[index] {
+                decl.kind = new_kind;
+            }
+
+            // 把原来的try语句换成一个裸的block语句，而不是把try块的内容拼接
+            // 进外层语句列表：try块自己的词法作用域（块级声明、与外层同名的
+            // 遮蔽绑定，包括资源绑定本身）必须原样保留，否则拼平之后可能
+            // 产生重复声明，把本来合法的代码变成语法错误。
+            let removed = statements.remove(index + 1);
+            let Statement::TryStatement(try_stmt) = removed else {
+                unreachable!("matched statement must still be a TryStatement");
+            };
+            let block = try_stmt.unbox().block;
+            statements.insert(index + 1, Statement::BlockStatement(block));
+
+            self.change_count += 1;
+            // 跳过刚刚插入的block：它就是原来try块的内容，不会再次匹配这个模式。
+            index += 1;
+        }
+
+        oxc_ast_visit::walk_mut::walk_statements(self, statements);
+    }
+}
+
+impl<'a> ModernizationPass<'a> for UsingDeclarationTransformer<'a> {
+    fn name(&self) -> &'static str {
+        "manual-cleanup-to-using"
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn priority(&self) -> i32 {
+        // 在var->let/const之后运行：这样原本是`var`、但已经被
+        // SmartVarToLetVisitor安全地转换成const/let的资源绑定，也能被
+        // 这个pass识别并进一步重写成using声明。
+        10
+    }
+
+    fn change_count(&self) -> usize {
+        self.change_count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TargetVersion, UsingDeclarationTransformer};
+    use oxc_allocator::Allocator;
+    use oxc_ast_visit::VisitMut;
+    use oxc_codegen::Codegen;
+    use oxc_parser::Parser;
+    use oxc_semantic::SemanticBuilder;
+    use oxc_span::SourceType;
+
+    #[test]
+    fn test_sync_cleanup_becomes_using() {
+        let result = test_transform(
+            "const file = acquire(); try { file.write(data); } finally { file.close(); }",
+            TargetVersion::EsNext,
+        );
+        assert!(result.contains("using file = acquire()"), "Expected conversion to a using declaration");
+        assert!(result.contains("file.write(data)"), "Expected the try block's body to be kept");
+        assert!(!result.contains("finally"), "Expected the try/finally wrapper to be removed");
+    }
+
+    #[test]
+    fn test_awaited_cleanup_becomes_await_using() {
+        let result = test_transform(
+            "const conn = acquire(); try { conn.query(sql); } finally { await conn.dispose(); }",
+            TargetVersion::EsNext,
+        );
+        assert!(result.contains("await using conn = acquire()"), "Expected conversion to an await using declaration");
+    }
+
+    #[test]
+    fn test_reassigned_binding_is_not_converted() {
+        let result = test_transform(
+            "let handle = acquire(); try { handle = reopen(handle); handle.use(); } finally { handle.close(); }",
+            TargetVersion::EsNext,
+        );
+        assert!(result.contains("try"), "Expected the try/finally to be preserved when the binding is reassigned");
+    }
+
+    #[test]
+    fn test_trailing_statement_after_try_prevents_conversion() {
+        // using在离开外层块（这里是函数体/程序）时才释放，但原来的
+        // doSomethingElse()在finally之后、函数结束之前执行：转换成using会
+        // 让doSomethingElse()在资源仍然打开的情况下运行，改变可观察行为。
+        let result = test_transform(
+            "const file = acquire(); try { file.write(data); } finally { file.close(); } doSomethingElse();",
+            TargetVersion::EsNext,
+        );
+        assert!(result.contains("try"), "Expected the try/finally to be preserved when it is not the last statement in its block");
+        assert!(!result.contains("using"), "Expected no using conversion when disposal timing would change");
+    }
+
+    #[test]
+    fn test_manual_dispose_on_one_path_prevents_conversion() {
+        // 某条路径在try块内部已经手动close()过一次：finally不是唯一的释放点，
+        // 转换成using会让这条路径触发两次释放。
+        let result = test_transform(
+            "function run() { const file = acquire(); try { if (x) { file.close(); return; } file.write(data); } finally { file.close(); } }",
+            TargetVersion::EsNext,
+        );
+        assert!(result.contains("try"), "Expected the try/finally to be preserved when the try body already disposes manually on some path");
+        assert!(!result.contains("using"), "Expected no using conversion when a manual dispose already exists in the try body");
+    }
+
+    #[test]
+    fn test_try_body_shadowing_binding_keeps_its_own_block_scope() {
+        // try块内部用let重新声明了一个与外层资源绑定同名的变量：拼平到外层
+        // 语句列表会产生重复声明，变成语法错误，所以try块自己的`{ }`必须保留。
+        let result = test_transform(
+            "const file = acquire(); try { let file = open(); file.read(); } finally { file.close(); }",
+            TargetVersion::EsNext,
+        );
+        assert!(result.contains("using file = acquire()"), "Expected conversion to a using declaration");
+        assert!(result.contains("let file = open()"), "Expected the shadowing let declaration to be preserved");
+        assert!(
+            result.matches('{').count() >= 1 && result.matches('}').count() >= 1,
+            "Expected the try block's own lexical scope to be kept as a nested block"
+        );
+    }
+
+    #[test]
+    fn test_unsupported_target_version_is_skipped() {
+        let result = test_transform(
+            "const file = acquire(); try { file.write(data); } finally { file.close(); }",
+            TargetVersion::Es2022,
+        );
+        assert!(result.contains("const file = acquire()"), "Expected the original const declaration to be preserved");
+        assert!(result.contains("finally"), "Expected the pass to be a no-op for a target version without using declarations");
+    }
+
+    pub fn test_transform(source_text: &str, target_version: TargetVersion) -> String {
+        let allocator = Allocator::default();
+        let source_type = SourceType::from_path("test.js").unwrap();
+        let ret = Parser::new(&allocator, source_text, source_type).parse();
+        if !ret.errors.is_empty() {
+            panic!("Parsing failed: {:?}", ret.errors);
+        }
+        let mut program = ret.program;
+
+        let semantic_ret = SemanticBuilder::new().build(&program);
+        if !semantic_ret.errors.is_empty() {
+            panic!("Semantic analysis failed: {:?}", semantic_ret.errors);
+        }
+        let semantic = semantic_ret.semantic;
+
+        let mut visitor = UsingDeclarationTransformer::new(&semantic, target_version);
+        visitor.visit_program(&mut program);
+
+        Codegen::new().build(&program).code
+    }
+}